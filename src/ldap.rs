@@ -1,8 +1,30 @@
+use ldap3_proto::proto::{LdapDerefAliases, LdapFilter, LdapSearchScope};
 use ldapcli::*;
 use structopt::StructOpt;
 
 include!("./ldap_opt.rs");
 
+impl From<SearchScopeArg> for LdapSearchScope {
+    fn from(value: SearchScopeArg) -> Self {
+        match value {
+            SearchScopeArg::Base => LdapSearchScope::Base,
+            SearchScopeArg::One => LdapSearchScope::OneLevel,
+            SearchScopeArg::Sub => LdapSearchScope::Subtree,
+        }
+    }
+}
+
+impl From<DerefAliasesArg> for LdapDerefAliases {
+    fn from(value: DerefAliasesArg) -> Self {
+        match value {
+            DerefAliasesArg::Never => LdapDerefAliases::Never,
+            DerefAliasesArg::Search => LdapDerefAliases::InSearching,
+            DerefAliasesArg::Find => LdapDerefAliases::FindingBaseObj,
+            DerefAliasesArg::Always => LdapDerefAliases::Always,
+        }
+    }
+}
+
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
     let opt = LdapOpt::from_args();
@@ -49,7 +71,18 @@ async fn main() {
         }
     };
 
-    let mut client = match LdapClient::new(&opt.url, timeout).await {
+    let mut tls_config_builder = TlsConfig::builder().insecure(opt.no_verify);
+    if let Some(cafile) = &opt.cafile {
+        tls_config_builder = tls_config_builder.ca_file(cafile.clone());
+    }
+    if let (Some(cert), Some(key)) = (&opt.cert, &opt.key) {
+        tls_config_builder = tls_config_builder
+            .client_cert(cert.clone())
+            .client_key(key.clone());
+    }
+    let tls_config = tls_config_builder.build();
+
+    let mut client = match LdapClient::new(&opt.url, timeout, tls_config).await {
         Ok(c) => c,
         Err(e) => {
             if opt.json {
@@ -64,7 +97,23 @@ async fn main() {
         }
     };
 
-    // The first message after connect is always a bind.
+    // If requested, StartTLS must be the very first operation after connect,
+    // before bind.
+    if opt.starttls {
+        if let Err(e) = client.start_tls().await {
+            if opt.json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&e).expect("CRITICAL: Serialisation Fault")
+                )
+            } else {
+                error!("Failed to start tls - {}", e);
+            }
+            std::process::exit(e as i32);
+        }
+    }
+
+    // The first message after connect (or after a StartTLS upgrade) is always a bind.
     if let Err(e) = client.bind(bind_dn, bind_passwd).await {
         if opt.json {
             println!(
@@ -78,7 +127,152 @@ async fn main() {
     };
 
     match opt.action {
-        LdapAction::Search => {}
-        LdapAction::Whoami(options) => {}
+        LdapAction::Search(options) => {
+            // The url may itself carry a base dn and ?attrs?scope?filter
+            // extensions per RFC 4516 - fall back to those for anything the
+            // CLI flags didn't override. Only bother the user about a
+            // malformed extension if something below would actually read it;
+            // if every field has already been overridden on the command
+            // line, just warn instead of aborting the search.
+            let needs_url_extensions = options.base.is_empty()
+                || options.scope.is_none()
+                || options.attrs.is_empty()
+                || options.filter.is_none();
+
+            let url_search = match (LdapUrlSearch::parse(&opt.url), needs_url_extensions) {
+                (Ok(u), _) => u,
+                (Err(e), true) => {
+                    if opt.json {
+                        println!(
+                            "{}",
+                            serde_json::to_string_pretty(&e)
+                                .expect("CRITICAL: Serialisation Fault")
+                        );
+                    } else {
+                        error!("Invalid search extensions in url - {}", e);
+                    }
+                    std::process::exit(e as i32);
+                }
+                (Err(e), false) => {
+                    warn!("Ignoring invalid search extensions in url - {}", e);
+                    LdapUrlSearch {
+                        base: String::new(),
+                        attrs: Vec::new(),
+                        scope: LdapSearchScope::Base,
+                        filter: LdapFilter::Present("objectClass".to_string()),
+                    }
+                }
+            };
+
+            let base = if options.base.is_empty() {
+                url_search.base
+            } else {
+                options.base
+            };
+
+            let scope = options
+                .scope
+                .map(Into::into)
+                .unwrap_or(url_search.scope);
+
+            let attrs = if options.attrs.is_empty() {
+                url_search.attrs
+            } else {
+                options.attrs
+            };
+
+            let filter = match options.filter {
+                Some(filter) => match ldap3_proto::filter::parse_ldap_filter_str(&filter) {
+                    Ok(f) => f,
+                    Err(e) => {
+                        let err = LdapError::InvalidFilter;
+                        if opt.json {
+                            println!(
+                                "{}",
+                                serde_json::to_string_pretty(&err)
+                                    .expect("CRITICAL: Serialisation Fault")
+                            );
+                        } else {
+                            error!("Invalid search filter - {}", e);
+                        }
+                        std::process::exit(err as i32);
+                    }
+                },
+                None => url_search.filter,
+            };
+
+            match client
+                .search(
+                    base,
+                    scope,
+                    options.aliases.into(),
+                    options.sizelimit,
+                    options.timelimit,
+                    options.typesonly,
+                    filter,
+                    attrs,
+                )
+                .await
+            {
+                Ok(results) => {
+                    if opt.json {
+                        println!(
+                            "{}",
+                            serde_json::to_string_pretty(&results.entries)
+                                .expect("CRITICAL: Serialisation Fault")
+                        );
+                    } else {
+                        for entry in &results.entries {
+                            println!("dn: {}", entry.dn);
+                            for attr in &entry.attributes {
+                                for val in &attr.vals {
+                                    println!("{}: {}", attr.atype, String::from_utf8_lossy(val));
+                                }
+                            }
+                            println!();
+                        }
+                    }
+                    for referral in &results.referrals {
+                        info!(%referral, "referral");
+                    }
+                }
+                Err(e) => {
+                    if opt.json {
+                        println!(
+                            "{}",
+                            serde_json::to_string_pretty(&e)
+                                .expect("CRITICAL: Serialisation Fault")
+                        );
+                    } else {
+                        error!("Search failed - {}", e);
+                    }
+                    std::process::exit(e as i32);
+                }
+            }
+        }
+        LdapAction::Whoami(_options) => match client.whoami().await {
+            Ok(authzid) => {
+                if opt.json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&authzid)
+                            .expect("CRITICAL: Serialisation Fault")
+                    );
+                } else {
+                    println!("{}", authzid);
+                }
+            }
+            Err(e) => {
+                if opt.json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&e).expect("CRITICAL: Serialisation Fault")
+                    );
+                } else {
+                    error!("Whoami failed - {}", e);
+                }
+                std::process::exit(e as i32);
+            }
+        },
     }
 }
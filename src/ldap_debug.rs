@@ -0,0 +1,230 @@
+use base64::Engine;
+use bytes::BytesMut;
+use ldap3_proto::proto::*;
+use ldap3_proto::LdapCodec;
+use ldapcli::*;
+use structopt::StructOpt;
+use tokio_util::codec::Decoder;
+
+include!("./ldap_debug_opt.rs");
+
+/// Normalise the raw bytes read from a dump file into a buffer of decoded
+/// DER/BER, ready to be fed to [`LdapCodec`].
+fn normalise(format: &DumpFormat, raw: &[u8]) -> Result<Vec<u8>, String> {
+    match format {
+        DumpFormat::OpenLDAPMemDump => parse_openldap_mem_dump(raw),
+        DumpFormat::Der => Ok(raw.to_vec()),
+        DumpFormat::Base64 => parse_base64_dump(raw),
+        DumpFormat::HexDump => parse_hex_dump(raw),
+    }
+}
+
+/// Is this a full wire capture that may contain multiple consecutive
+/// messages, or a single, possibly incomplete, fragment?
+fn is_wire_capture(format: &DumpFormat) -> bool {
+    match format {
+        DumpFormat::OpenLDAPMemDump => false,
+        DumpFormat::Der | DumpFormat::Base64 | DumpFormat::HexDump => true,
+    }
+}
+
+/// Parse a formatted array of bytes taken from an openldap memory dump, for
+/// example `[0x00, 0x01, 0x02, ...]`.
+fn parse_openldap_mem_dump(raw: &[u8]) -> Result<Vec<u8>, String> {
+    let text = std::str::from_utf8(raw).map_err(|e| format!("input is not valid utf8 - {}", e))?;
+
+    text.trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(',')
+        .map(str::trim)
+        .filter(|tok| !tok.is_empty())
+        .map(|tok| {
+            let tok = tok.trim_start_matches("0x").trim_start_matches("0X");
+            u8::from_str_radix(tok, 16).map_err(|e| format!("invalid byte literal '{}' - {}", tok, e))
+        })
+        .collect()
+}
+
+/// Decode a base64 encoded DER/BER blob, ignoring any surrounding whitespace.
+fn parse_base64_dump(raw: &[u8]) -> Result<Vec<u8>, String> {
+    let text: String = std::str::from_utf8(raw)
+        .map_err(|e| format!("input is not valid utf8 - {}", e))?
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+
+    base64::engine::general_purpose::STANDARD
+        .decode(text)
+        .map_err(|e| format!("invalid base64 - {}", e))
+}
+
+/// If this whitespace-separated token is an offset/address column, as
+/// emitted by the hex dumps of `tcpdump -xx` (`0x0000:`) and Wireshark's
+/// "Follow TCP Stream -> Hex Dump" (`00000000`), return its numeric value.
+fn parse_offset_token(token: &str) -> Option<u64> {
+    let stripped = token.strip_suffix(':').unwrap_or(token);
+    let is_offset_shaped = token.ends_with(':') || stripped.len() >= 6;
+    let stripped = stripped
+        .strip_prefix("0x")
+        .or_else(|| stripped.strip_prefix("0X"))
+        .unwrap_or(stripped);
+    if !is_offset_shaped || stripped.is_empty() || !stripped.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u64::from_str_radix(stripped, 16).ok()
+}
+
+/// Does this token (after stripping an optional `0x` prefix) look like a
+/// byte group: a non-empty, even-length run of hex digits?
+fn is_hex_token(token: &str) -> bool {
+    !token.is_empty() && token.len() % 2 == 0 && token.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Parse a hex stream copied from `tcpdump`/Wireshark "follow TCP stream"
+/// output, tolerating leading offset columns and arbitrary whitespace.
+///
+/// Wireshark's hex dump view has no delimiter between the byte columns and
+/// the trailing ASCII sidebar, so a naive "does this token look like hex"
+/// scan can mistake printable ASCII (e.g. the literal text `cafe`) for more
+/// payload bytes - including on the final line, where there is no following
+/// offset to derive a byte-count budget from. Byte-group tokens are always
+/// a fixed width for a given capture (2 hex chars per byte for xxd/hexdump,
+/// 4 per 16-bit word for `tcpdump -xx`), so in addition to the offset-delta
+/// budget we require every token to match the width of the first real
+/// byte-group token seen in the dump, which the ASCII sidebar essentially
+/// never does by coincidence.
+fn parse_hex_dump(raw: &[u8]) -> Result<Vec<u8>, String> {
+    let text = std::str::from_utf8(raw).map_err(|e| format!("input is not valid utf8 - {}", e))?;
+
+    // Split each line into its offset (if any) and the rest of the line.
+    let lines: Vec<(Option<u64>, &str)> = text
+        .lines()
+        .map(|line| match line.split_whitespace().next() {
+            Some(first) if parse_offset_token(first).is_some() => {
+                let rest = line[line.find(first).unwrap() + first.len()..].trim_start();
+                (parse_offset_token(first), rest)
+            }
+            _ => (None, line),
+        })
+        .collect();
+
+    let token_width = lines
+        .iter()
+        .flat_map(|(_, rest)| rest.split_whitespace())
+        .map(|tok| tok.trim_start_matches("0x").trim_start_matches("0X"))
+        .find(|tok| is_hex_token(tok))
+        .map(str::len);
+
+    let mut hex = String::new();
+    for (idx, (offset, rest)) in lines.iter().enumerate() {
+        let next_offset = lines.get(idx + 1).and_then(|(o, _)| *o);
+        let budget = match (offset, next_offset) {
+            (Some(cur), Some(next)) if next > *cur => Some(((next - cur) * 2) as usize),
+            _ => None,
+        };
+
+        let mut taken = 0;
+        for token in rest.split_whitespace() {
+            if budget.is_some_and(|budget| taken >= budget) {
+                break;
+            }
+            let token = token.trim_start_matches("0x").trim_start_matches("0X");
+            if !is_hex_token(token) || token_width.is_some_and(|width| token.len() != width) {
+                // Not a byte group - either the trailing ASCII sidebar or
+                // malformed input. Either way, nothing after it on this
+                // line is payload.
+                break;
+            }
+            let take = match budget {
+                Some(budget) => token.len().min(budget - taken),
+                None => token.len(),
+            };
+            hex.push_str(&token[..take]);
+            taken += take;
+        }
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|e| format!("invalid hex byte '{}' - {}", &hex[i..i + 2], e))
+        })
+        .collect()
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let opt = LdapDebugOpt::from_args();
+    ldapcli::start_tracing(opt.verbose);
+
+    match opt.action {
+        LdapDebugAction::BerDump(dump_opt) => ber_dump(dump_opt),
+    }
+}
+
+fn ber_dump(dump_opt: BerDumpOptions) {
+    let raw = match std::fs::read(&dump_opt.path) {
+        Ok(raw) => raw,
+        Err(e) => {
+            error!("Unable to read {} - {}", dump_opt.path.display(), e);
+            std::process::exit(1);
+        }
+    };
+
+    let buf = match normalise(&dump_opt.format, &raw) {
+        Ok(buf) => buf,
+        Err(e) => {
+            error!("Unable to parse dump as {} - {}", dump_opt.format, e);
+            std::process::exit(1);
+        }
+    };
+
+    let total_len = buf.len();
+    let mut buf = BytesMut::from(buf.as_slice());
+    let mut codec = LdapCodec;
+    let mut count = 0;
+
+    loop {
+        let offset = total_len - buf.len();
+        match codec.decode(&mut buf) {
+            Ok(Some(msg)) => {
+                println!("--- message {} (offset {:#x}) ---", count, offset);
+                println!("{:#?}", msg.op);
+                count += 1;
+            }
+            Ok(None) => break,
+            Err(e) => {
+                error!(
+                    "Failed to decode message {} at offset {:#x} ({} bytes remaining) - {:?}",
+                    count,
+                    offset,
+                    buf.len(),
+                    e
+                );
+                std::process::exit(1);
+            }
+        }
+
+        if !is_wire_capture(&dump_opt.format) {
+            break;
+        }
+    }
+
+    if count == 0 {
+        error!("No messages decoded");
+        std::process::exit(1);
+    }
+
+    if !buf.is_empty() {
+        if is_wire_capture(&dump_opt.format) {
+            warn!("{} trailing undecoded bytes in buffer", buf.len());
+        } else {
+            warn!(
+                "{} trailing bytes in buffer - this is expected for a partial openldap_mem_dump fragment",
+                buf.len()
+            );
+        }
+    }
+}
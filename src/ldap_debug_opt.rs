@@ -6,6 +6,9 @@ use std::str::FromStr;
 #[derive(Debug, StructOpt)]
 enum DumpFormat {
     OpenLDAPMemDump,
+    Der,
+    Base64,
+    HexDump,
 }
 
 impl FromStr for DumpFormat {
@@ -14,14 +17,22 @@ impl FromStr for DumpFormat {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "openldap_mem_dump" => Ok(DumpFormat::OpenLDAPMemDump),
-            _ => Err("Unknown DumpFormat. Valid choices are openldap_mem_dump"),
+            "der" => Ok(DumpFormat::Der),
+            "base64" => Ok(DumpFormat::Base64),
+            "hex_dump" => Ok(DumpFormat::HexDump),
+            _ => Err(
+                "Unknown DumpFormat. Valid choices are openldap_mem_dump, der, base64, hex_dump",
+            ),
         }
     }
 }
 
 impl fmt::Display for DumpFormat {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "DumpFormats: openldap_mem_dump_json")
+        write!(
+            f,
+            "DumpFormats: openldap_mem_dump, der, base64, hex_dump"
+        )
     }
 }
 
@@ -35,6 +46,19 @@ struct BerDumpOptions {
     /// Since this has been partially pre-processed by openldap, this is not a full
     /// valid message. An example is `[0x00, 0x01, 0x02, ...]`
     ///
+    /// * der
+    /// A raw DER/BER binary file, such as a wire capture saved directly to disk.
+    ///
+    /// * base64
+    /// A base64 encoded DER/BER blob. Surrounding whitespace is ignored.
+    ///
+    /// * hex_dump
+    /// A hex stream copied from `tcpdump -xx` or Wireshark's "Follow TCP
+    /// Stream -> Hex Dump" view. Offset columns and whitespace are tolerated.
+    ///
+    /// Unlike openldap_mem_dump, these are full wire captures and may contain
+    /// more than one message, which are decoded consecutively until the
+    /// buffer is exhausted.
     #[structopt(short, long)]
     format: DumpFormat,
 
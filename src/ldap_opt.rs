@@ -1,3 +1,84 @@
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy)]
+enum SearchScopeArg {
+    Base,
+    One,
+    Sub,
+}
+
+impl FromStr for SearchScopeArg {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "base" => Ok(SearchScopeArg::Base),
+            "one" => Ok(SearchScopeArg::One),
+            "sub" => Ok(SearchScopeArg::Sub),
+            _ => Err("Unknown scope. Valid choices are base, one, sub"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DerefAliasesArg {
+    Never,
+    Search,
+    Find,
+    Always,
+}
+
+impl FromStr for DerefAliasesArg {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(DerefAliasesArg::Never),
+            "search" => Ok(DerefAliasesArg::Search),
+            "find" => Ok(DerefAliasesArg::Find),
+            "always" => Ok(DerefAliasesArg::Always),
+            _ => Err("Unknown deref-aliases policy. Valid choices are never, search, find, always"),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct SearchOptions {
+    #[structopt(short = "b", long = "basedn", default_value = "")]
+    /// The base dn to search from. If not given, and the url contains a base
+    /// dn, that is used instead.
+    base: String,
+
+    #[structopt(short = "s", long = "scope")]
+    /// The search scope to use: base, one or sub. If not given, the scope
+    /// from the url's extensions is used, defaulting to base.
+    scope: Option<SearchScopeArg>,
+
+    #[structopt(short = "a", long = "deref-aliases", default_value = "never")]
+    /// When to dereference aliases: never, search, find or always
+    aliases: DerefAliasesArg,
+
+    #[structopt(long = "sizelimit")]
+    /// The maximum number of entries to return, 0 for no limit
+    sizelimit: Option<i32>,
+
+    #[structopt(long = "timelimit")]
+    /// The maximum number of seconds the server may spend on the search, 0 for no limit
+    timelimit: Option<i32>,
+
+    #[structopt(long = "types-only")]
+    /// Only return attribute types, not their values
+    typesonly: bool,
+
+    /// The search filter to apply, for example (objectClass=*). If not
+    /// given, the filter from the url's extensions is used, defaulting to
+    /// (objectClass=*).
+    filter: Option<String>,
+
+    /// The attributes to request. If not given, the attributes from the
+    /// url's extensions are used, defaulting to all user attributes.
+    attrs: Vec<String>,
+}
 
 #[derive(Debug, StructOpt)]
 struct WhoamiOptions {
@@ -8,7 +89,7 @@ struct WhoamiOptions {
 #[derive(Debug, StructOpt)]
 enum LdapAction {
     /// Search a directory server
-    Search,
+    Search(SearchOptions),
     /// Check authentication (bind) to a directory server
     Whoami(WhoamiOptions)
 }
@@ -32,6 +113,26 @@ struct LdapOpt {
     #[structopt(short = "w", long = "pass")]
     bind_passwd: Option<String>,
 
+    #[structopt(long = "starttls")]
+    /// Upgrade a plaintext ldap:// connection to TLS via StartTLS before binding
+    starttls: bool,
+
+    #[structopt(long = "cafile", parse(from_os_str))]
+    /// A PEM file of CA certificate(s) to trust instead of the system trust store
+    cafile: Option<std::path::PathBuf>,
+
+    #[structopt(long = "cert", parse(from_os_str), requires = "key")]
+    /// A PEM client certificate to present for mutual TLS
+    cert: Option<std::path::PathBuf>,
+
+    #[structopt(long = "key", parse(from_os_str), requires = "cert")]
+    /// The private key for --cert
+    key: Option<std::path::PathBuf>,
+
+    #[structopt(long = "no-verify")]
+    /// Disable TLS certificate and hostname verification. Dangerous - only use for testing
+    no_verify: bool,
+
     #[structopt(flatten)]
     /// The ldap action to perform
     action: LdapAction
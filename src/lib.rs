@@ -13,13 +13,24 @@ use futures_util::stream::StreamExt;
 
 use ldap3_proto::proto::*;
 use ldap3_proto::LdapCodec;
-use openssl::ssl::{Ssl, SslConnector, SslMethod, SslVerifyMode};
+use openssl::ssl::{SslConnector, SslFiletype, SslMethod, SslVerifyMode};
+use openssl::x509::store::X509StoreBuilder;
+use openssl::x509::{X509VerifyResult, X509};
+use percent_encoding::percent_decode_str;
 use tokio_openssl::SslStream;
 use tokio_util::codec::{Framed, FramedRead, FramedWrite};
 
+use std::collections::HashMap;
 use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::{broadcast, mpsc, oneshot, Mutex as AsyncMutex};
 use url::Url;
 
+// https://www.openssl.org/docs/man1.1.1/man3/X509_verify_cert_error_string.html
+const X509_V_ERR_HOSTNAME_MISMATCH: i32 = 62;
+
 pub fn start_tracing(verbose: bool) {
     let fmt_layer = tracing_subscriber::fmt::layer().with_target(false);
     let filter_layer = EnvFilter::try_from_default_env()
@@ -52,6 +63,11 @@ pub enum LdapError {
     TransportWriteError = -9,
     TransportReadError = -10,
     InvalidProtocolState = -11,
+    InvalidFilter = -12,
+    OperationError = -13,
+    TlsVerifyError = -14,
+    TlsHostnameError = -15,
+    Abandoned = -16,
 
     InvalidCredentials = 49,
 }
@@ -60,7 +76,7 @@ impl From<LdapResultCode> for LdapError {
     fn from(code: LdapResultCode) -> Self {
         match code {
             LdapResultCode::InvalidCredentials => LdapError::InvalidCredentials,
-            _ => unimplemented!(),
+            _ => LdapError::OperationError,
         }
     }
 }
@@ -74,17 +90,28 @@ impl fmt::Display for LdapError {
             LdapError::ResolverError => write!(f, "Failed to resolve hostname or invalid ip"),
             LdapError::ConnectError => write!(f, "Failed to connect to host"),
             LdapError::TlsError => write!(f, "Failed to establish TLS"),
+            LdapError::TlsVerifyError => {
+                write!(f, "The server's TLS certificate is not trusted")
+            }
+            LdapError::TlsHostnameError => {
+                write!(f, "The server's TLS certificate does not match the requested hostname")
+            }
             LdapError::PasswordNotFound => write!(f, "No password available for bind"),
             LdapError::AnonymousInvalidState => write!(f, "Invalid Anonymous bind state"),
             LdapError::InvalidProtocolState => {
                 write!(f, "The LDAP server sent a response we did not expect")
             }
+            LdapError::InvalidFilter => write!(f, "Invalid search filter"),
+            LdapError::OperationError => {
+                write!(f, "The directory server returned an error for this operation")
+            }
             LdapError::TransportReadError => {
                 write!(f, "An error occured reading from the transport")
             }
             LdapError::TransportWriteError => {
                 write!(f, "An error occured writing to the transport")
             }
+            LdapError::Abandoned => write!(f, "The operation was abandoned"),
 
             LdapError::InvalidCredentials => write!(f, "Invalid DN or Password"),
         }
@@ -93,6 +120,143 @@ impl fmt::Display for LdapError {
 
 pub type LdapResult<T> = Result<T, LdapError>;
 
+/// <https://datatracker.ietf.org/doc/html/rfc4511#section-4.14.1>
+const OID_START_TLS: &str = "1.3.6.1.4.1.1466.20037";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LdapSearchResult {
+    pub entries: Vec<LdapSearchResultEntry>,
+    pub referrals: Vec<String>,
+}
+
+/// The components of an LDAP URL's base DN and `?`-delimited search
+/// extensions, as defined by <https://datatracker.ietf.org/doc/html/rfc4516>:
+/// `ldap://host/base?attrs?scope?filter`. Each extension is optional and
+/// percent-decoded, defaulting to all user attributes, base-object scope and
+/// `(objectClass=*)` when absent.
+#[derive(Debug, Clone)]
+pub struct LdapUrlSearch {
+    pub base: String,
+    pub attrs: Vec<String>,
+    pub scope: LdapSearchScope,
+    pub filter: LdapFilter,
+}
+
+impl LdapUrlSearch {
+    pub fn parse(url: &Url) -> LdapResult<Self> {
+        let base = percent_decode_str(url.path().trim_start_matches('/'))
+            .decode_utf8_lossy()
+            .into_owned();
+
+        let mut extensions = url.query().unwrap_or("").splitn(3, '?');
+        let attrs_ext = extensions.next().unwrap_or("");
+        let scope_ext = extensions.next().unwrap_or("");
+        let filter_ext = extensions.next().unwrap_or("");
+
+        let attrs = if attrs_ext.is_empty() {
+            Vec::new()
+        } else {
+            attrs_ext
+                .split(',')
+                .map(|a| percent_decode_str(a).decode_utf8_lossy().into_owned())
+                .collect()
+        };
+
+        let scope_ext = percent_decode_str(scope_ext).decode_utf8_lossy().into_owned();
+        let scope = match scope_ext.as_str() {
+            "" | "base" => LdapSearchScope::Base,
+            "one" => LdapSearchScope::OneLevel,
+            "sub" => LdapSearchScope::Subtree,
+            _ => return Err(LdapError::InvalidUrl),
+        };
+
+        let filter = if filter_ext.is_empty() {
+            LdapFilter::Present("objectClass".to_string())
+        } else {
+            let filter_ext = percent_decode_str(filter_ext).decode_utf8_lossy().into_owned();
+            ldap3_proto::filter::parse_ldap_filter_str(&filter_ext).map_err(|e| {
+                info!(?e, "invalid filter in ldap url");
+                LdapError::InvalidFilter
+            })?
+        };
+
+        Ok(LdapUrlSearch {
+            base,
+            attrs,
+            scope,
+            filter,
+        })
+    }
+}
+
+/// Configuration for how a `ldaps://` connection, or a `ldap://` connection
+/// upgraded via StartTLS, verifies the server's certificate.
+///
+/// By default the server certificate is verified against the system trust
+/// store and the connection hostname, matching a normal TLS client. Use
+/// [`TlsConfig::builder`] to opt into a custom CA, client certificate, or
+/// (not recommended) to disable verification entirely.
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    ca_file: Option<PathBuf>,
+    ca_dir: Option<PathBuf>,
+    client_cert: Option<PathBuf>,
+    client_key: Option<PathBuf>,
+    insecure: bool,
+}
+
+impl TlsConfig {
+    pub fn builder() -> TlsConfigBuilder {
+        TlsConfigBuilder::default()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfigBuilder {
+    inner: TlsConfig,
+}
+
+impl TlsConfigBuilder {
+    /// Trust only the CA certificate(s) in this PEM file, instead of the
+    /// system trust store.
+    pub fn ca_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.inner.ca_file = Some(path.into());
+        self
+    }
+
+    /// Trust only the CA certificates in this openssl hashed directory,
+    /// instead of the system trust store.
+    pub fn ca_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.inner.ca_dir = Some(path.into());
+        self
+    }
+
+    /// Present this client certificate for mutual TLS. Must be paired with
+    /// [`TlsConfigBuilder::client_key`].
+    pub fn client_cert(mut self, path: impl Into<PathBuf>) -> Self {
+        self.inner.client_cert = Some(path.into());
+        self
+    }
+
+    /// The private key for the client certificate set with
+    /// [`TlsConfigBuilder::client_cert`].
+    pub fn client_key(mut self, path: impl Into<PathBuf>) -> Self {
+        self.inner.client_key = Some(path.into());
+        self
+    }
+
+    /// Disable all certificate and hostname verification. This must be
+    /// explicitly opted into - it is never the default.
+    pub fn insecure(mut self, insecure: bool) -> Self {
+        self.inner.insecure = insecure;
+        self
+    }
+
+    pub fn build(self) -> TlsConfig {
+        self.inner
+    }
+}
+
 enum LdapReadTransport {
     Plain(FramedRead<ReadHalf<TcpStream>, LdapCodec>),
     Tls(FramedRead<ReadHalf<SslStream<TcpStream>>, LdapCodec>),
@@ -169,121 +333,261 @@ impl LdapReadTransport {
 
 #[derive(Debug)]
 pub struct LdapClient {
-    read_transport: LdapReadTransport,
-    write_transport: LdapWriteTransport,
+    // These are `Option` so that `start_tls` can briefly take ownership of the
+    // plaintext framed halves, reclaim the underlying `TcpStream` and rebuild
+    // them as TLS. Outside of that upgrade they are always `Some`, except
+    // that a failed TLS handshake during `start_tls` leaves both `None`, after
+    // which the client can no longer be used.
+    read_transport: Option<LdapReadTransport>,
+    write_transport: Option<LdapWriteTransport>,
     msg_counter: i32,
+    // The hostname the connection was made to, and the verification policy
+    // to apply - both are needed again if `start_tls` later upgrades the
+    // connection.
+    host: String,
+    tls_config: TlsConfig,
 }
 
-impl LdapClient {
-    #[tracing::instrument(level = "debug", skip_all)]
-    pub async fn new(url: &Url, timeout: Duration) -> LdapResult<Self> {
-        info!(%url);
-        info!(?timeout);
-
-        // Check the scheme is ldap or ldaps
-        // for now, no ldapi support.
-        let need_tls = match url.scheme() {
-            "ldapi" => return Err(LdapError::LdapiNotSupported),
-            "cldap" => return Err(LdapError::UseCldapTool),
-            "ldap" => false,
-            "ldaps" => true,
-            _ => return Err(LdapError::InvalidUrl),
-        };
+/// Resolve and connect to `url`, performing the TLS handshake up front for
+/// `ldaps://`. Shared by [`LdapClient::new`] and [`LdapClientMux::new`] since
+/// both drive the same connect-then-optionally-upgrade sequence.
+#[tracing::instrument(level = "debug", skip_all)]
+async fn connect(
+    url: &Url,
+    timeout: Duration,
+    tls_config: &TlsConfig,
+) -> LdapResult<(LdapWriteTransport, LdapReadTransport, String)> {
+    info!(%url);
+    info!(?timeout);
 
-        info!(%need_tls);
-        // get domain + port
+    // Check the scheme is ldap or ldaps
+    // for now, no ldapi support.
+    let need_tls = match url.scheme() {
+        "ldapi" => return Err(LdapError::LdapiNotSupported),
+        "cldap" => return Err(LdapError::UseCldapTool),
+        "ldap" => false,
+        "ldaps" => true,
+        _ => return Err(LdapError::InvalidUrl),
+    };
 
-        // Do we have query params? Can we use them?
-        // https://ldap.com/ldap-urls/
+    let host = url.host_str().ok_or(LdapError::InvalidUrl)?.to_string();
 
-        // resolve to a set of socket addrs.
-        let addrs = url
-            .socket_addrs(|| Some(if need_tls { 636 } else { 389 }))
-            .map_err(|e| {
-                info!(?e, "resolver error");
-                LdapError::ResolverError
-            })?;
+    info!(%need_tls);
+    // get domain + port
 
-        if addrs.is_empty() {
-            return Err(LdapError::ResolverError);
-        }
+    // Do we have query params? Can we use them?
+    // https://ldap.com/ldap-urls/
 
-        addrs.iter().for_each(|address| info!(?address));
-
-        let mut aiter = addrs.into_iter();
-
-        // Try for each to open, with a timeout.
-        let tcpstream = loop {
-            if let Some(addr) = aiter.next() {
-                let sleep = time::sleep(timeout);
-                tokio::pin!(sleep);
-                tokio::select! {
-                    maybe_stream = TcpStream::connect(addr) => {
-                        match maybe_stream {
-                            Ok(t) => {
-                                info!(?addr, "connection established");
-                                break t;
-                            }
-                            Err(e) => {
-                                info!(?addr, ?e, "error");
-                                continue;
-                            }
+    // resolve to a set of socket addrs.
+    let addrs = url
+        .socket_addrs(|| Some(if need_tls { 636 } else { 389 }))
+        .map_err(|e| {
+            info!(?e, "resolver error");
+            LdapError::ResolverError
+        })?;
+
+    if addrs.is_empty() {
+        return Err(LdapError::ResolverError);
+    }
+
+    addrs.iter().for_each(|address| info!(?address));
+
+    let mut aiter = addrs.into_iter();
+
+    // Try for each to open, with a timeout.
+    let tcpstream = loop {
+        if let Some(addr) = aiter.next() {
+            let sleep = time::sleep(timeout);
+            tokio::pin!(sleep);
+            tokio::select! {
+                maybe_stream = TcpStream::connect(addr) => {
+                    match maybe_stream {
+                        Ok(t) => {
+                            info!(?addr, "connection established");
+                            break t;
+                        }
+                        Err(e) => {
+                            info!(?addr, ?e, "error");
+                            continue;
                         }
-                    }
-                    _ = &mut sleep => {
-                        info!(?addr, "timeout");
-                        continue;
                     }
                 }
-            } else {
-                return Err(LdapError::ConnectError);
+                _ = &mut sleep => {
+                    info!(?addr, "timeout");
+                    continue;
+                }
             }
-        };
+        } else {
+            return Err(LdapError::ConnectError);
+        }
+    };
 
-        // If ldaps - start openssl
-        let (write_transport, read_transport) = if need_tls {
-            let mut tls_parms = SslConnector::builder(SslMethod::tls_client()).map_err(|e| {
-                info!(?e, "openssl");
-                LdapError::TlsError
-            })?;
-            tls_parms.set_verify(SslVerifyMode::NONE);
-            let tls_parms = tls_parms.build();
+    // If ldaps - start openssl
+    let (write_transport, read_transport) = if need_tls {
+        LdapClient::tls_upgrade(tcpstream, &host, tls_config).await?
+    } else {
+        let (r, w) = tokio::io::split(tcpstream);
+        (
+            LdapWriteTransport::Plain(FramedWrite::new(w, LdapCodec)),
+            LdapReadTransport::Plain(FramedRead::new(r, LdapCodec)),
+        )
+    };
+
+    Ok((write_transport, read_transport, host))
+}
+
+impl LdapClient {
+    fn read_transport(&mut self) -> &mut LdapReadTransport {
+        self.read_transport
+            .as_mut()
+            .expect("read transport missing - this is a bug")
+    }
+
+    fn write_transport(&mut self) -> &mut LdapWriteTransport {
+        self.write_transport
+            .as_mut()
+            .expect("write transport missing - this is a bug")
+    }
+
+    async fn tls_upgrade(
+        tcpstream: TcpStream,
+        host: &str,
+        tls_config: &TlsConfig,
+    ) -> LdapResult<(LdapWriteTransport, LdapReadTransport)> {
+        // `SslConnector::builder` already defaults to `SslVerifyMode::PEER`
+        // plus the system trust store - we only need to touch this when the
+        // caller wants something different.
+        let mut ctx_builder = SslConnector::builder(SslMethod::tls_client()).map_err(|e| {
+            info!(?e, "openssl");
+            LdapError::TlsError
+        })?;
+
+        if tls_config.insecure {
+            ctx_builder.set_verify(SslVerifyMode::NONE);
+        } else if tls_config.ca_file.is_some() || tls_config.ca_dir.is_some() {
+            // An explicit CA was given - trust only that, not the system store.
+            let cert_store = Self::build_custom_trust_store(tls_config)?;
+            ctx_builder.set_cert_store(cert_store);
+        }
 
-            let mut tlsstream = Ssl::new(tls_parms.context())
-                .and_then(|tls_obj| SslStream::new(tls_obj, tcpstream))
+        if let (Some(cert), Some(key)) = (&tls_config.client_cert, &tls_config.client_key) {
+            ctx_builder
+                .set_certificate_file(cert, SslFiletype::PEM)
                 .map_err(|e| {
                     info!(?e, "openssl");
                     LdapError::TlsError
                 })?;
-
-            let _ = SslStream::connect(Pin::new(&mut tlsstream))
-                .await
+            ctx_builder
+                .set_private_key_file(key, SslFiletype::PEM)
                 .map_err(|e| {
                     info!(?e, "openssl");
                     LdapError::TlsError
                 })?;
+        }
 
-            info!("tls configured");
-            let (r, w) = tokio::io::split(tlsstream);
-            (
-                LdapWriteTransport::Tls(FramedWrite::new(w, LdapCodec)),
-                LdapReadTransport::Tls(FramedRead::new(r, LdapCodec)),
-            )
-        } else {
-            let (r, w) = tokio::io::split(tcpstream);
-            (
-                LdapWriteTransport::Plain(FramedWrite::new(w, LdapCodec)),
-                LdapReadTransport::Plain(FramedRead::new(r, LdapCodec)),
-            )
-        };
+        let connector = ctx_builder.build();
+
+        let mut configuration = connector.configure().map_err(|e| {
+            info!(?e, "openssl");
+            LdapError::TlsError
+        })?;
+
+        if tls_config.insecure {
+            configuration.set_verify_hostname(false);
+        }
+
+        let ssl = configuration.into_ssl(host).map_err(|e| {
+            info!(?e, "openssl");
+            LdapError::TlsError
+        })?;
+
+        let mut tlsstream = SslStream::new(ssl, tcpstream).map_err(|e| {
+            info!(?e, "openssl");
+            LdapError::TlsError
+        })?;
+
+        if let Err(e) = SslStream::connect(Pin::new(&mut tlsstream)).await {
+            let verify_result = tlsstream.ssl().verify_result();
+            info!(?e, ?verify_result, "tls handshake failed");
+            return Err(if verify_result == X509VerifyResult::OK {
+                LdapError::TlsError
+            } else if verify_result.as_raw() == X509_V_ERR_HOSTNAME_MISMATCH {
+                LdapError::TlsHostnameError
+            } else {
+                LdapError::TlsVerifyError
+            });
+        }
+
+        info!("tls configured");
+        let (r, w) = tokio::io::split(tlsstream);
+        Ok((
+            LdapWriteTransport::Tls(FramedWrite::new(w, LdapCodec)),
+            LdapReadTransport::Tls(FramedRead::new(r, LdapCodec)),
+        ))
+    }
+
+    fn build_custom_trust_store(tls_config: &TlsConfig) -> LdapResult<openssl::x509::store::X509Store> {
+        let mut store_builder = X509StoreBuilder::new().map_err(|e| {
+            info!(?e, "openssl");
+            LdapError::TlsError
+        })?;
+
+        if let Some(ca_file) = &tls_config.ca_file {
+            let pem = std::fs::read(ca_file).map_err(|e| {
+                info!(?e, ?ca_file, "failed to read ca file");
+                LdapError::TlsError
+            })?;
+            let certs = X509::stack_from_pem(&pem).map_err(|e| {
+                info!(?e, "openssl");
+                LdapError::TlsError
+            })?;
+            for cert in certs {
+                store_builder.add_cert(cert).map_err(|e| {
+                    info!(?e, "openssl");
+                    LdapError::TlsError
+                })?;
+            }
+        }
+
+        if let Some(ca_dir) = &tls_config.ca_dir {
+            Self::add_ca_dir(&mut store_builder, ca_dir)?;
+        }
+
+        Ok(store_builder.build())
+    }
+
+    fn add_ca_dir(
+        store_builder: &mut X509StoreBuilder,
+        ca_dir: &Path,
+    ) -> LdapResult<()> {
+        use openssl::x509::store::X509Lookup;
+
+        let ca_dir = ca_dir.to_str().ok_or(LdapError::TlsError)?;
+
+        let lookup = store_builder
+            .add_lookup(X509Lookup::hash_dir())
+            .map_err(|e| {
+                info!(?e, "openssl");
+                LdapError::TlsError
+            })?;
+
+        lookup.add_dir(ca_dir, SslFiletype::PEM).map_err(|e| {
+            info!(?e, "openssl");
+            LdapError::TlsError
+        })
+    }
 
-        let msg_counter = 1;
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn new(url: &Url, timeout: Duration, tls_config: TlsConfig) -> LdapResult<Self> {
+        let (write_transport, read_transport, host) =
+            connect(url, timeout, &tls_config).await?;
 
-        // Good to go - return ok!
         Ok(LdapClient {
-            read_transport,
-            write_transport,
-            msg_counter,
+            read_transport: Some(read_transport),
+            write_transport: Some(write_transport),
+            msg_counter: 1,
+            host,
+            tls_config,
         })
     }
 
@@ -307,10 +611,10 @@ impl LdapClient {
             ctrl: vec![],
         };
 
-        self.write_transport.send(msg).await?;
+        self.write_transport().send(msg).await?;
 
         // Get the response
-        self.read_transport
+        self.read_transport()
             .next()
             .await
             .and_then(|msg| match msg.op {
@@ -329,4 +633,506 @@ impl LdapClient {
                 }
             })
     }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search(
+        &mut self,
+        base: String,
+        scope: LdapSearchScope,
+        aliases: LdapDerefAliases,
+        sizelimit: Option<i32>,
+        timelimit: Option<i32>,
+        typesonly: bool,
+        filter: LdapFilter,
+        attrs: Vec<String>,
+    ) -> LdapResult<LdapSearchResult> {
+        info!(%base);
+        let msgid = self.get_next_msgid();
+
+        let msg = LdapMsg {
+            msgid,
+            op: LdapOp::SearchRequest(LdapSearchRequest {
+                base,
+                scope,
+                aliases,
+                sizelimit: sizelimit.unwrap_or(0),
+                timelimit: timelimit.unwrap_or(0),
+                typesonly,
+                filter,
+                attrs,
+            }),
+            ctrl: vec![],
+        };
+
+        self.write_transport().send(msg).await?;
+
+        let mut entries = Vec::new();
+        let mut referrals = Vec::new();
+
+        loop {
+            let msg = self.read_transport().next().await?;
+
+            if msg.msgid != msgid {
+                info!(got = ?msg.msgid, expect = ?msgid, "ignoring unexpected msgid");
+                continue;
+            }
+
+            match msg.op {
+                LdapOp::SearchResultEntry(entry) => entries.push(entry),
+                LdapOp::SearchResultReference(reference) => referrals.extend(reference.uris),
+                LdapOp::SearchResultDone(res) => {
+                    return if res.code == LdapResultCode::Success {
+                        info!(entries = %entries.len(), "search success");
+                        Ok(LdapSearchResult { entries, referrals })
+                    } else {
+                        info!(?res.code);
+                        Err(LdapError::from(res.code))
+                    };
+                }
+                _ => return Err(LdapError::InvalidProtocolState),
+            }
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn whoami(&mut self) -> LdapResult<String> {
+        let msgid = self.get_next_msgid();
+
+        let msg = LdapMsg {
+            msgid,
+            op: LdapOp::ExtendedRequest(LdapExtendedRequest {
+                name: OID_WHOAMI.to_string(),
+                value: None,
+            }),
+            ctrl: vec![],
+        };
+
+        self.write_transport().send(msg).await?;
+
+        self.read_transport()
+            .next()
+            .await
+            .and_then(|msg| match msg.op {
+                LdapOp::ExtendedResponse(res) => {
+                    if res.res.code == LdapResultCode::Success {
+                        let authzid = res
+                            .value
+                            .map(|v| String::from_utf8_lossy(&v).into_owned())
+                            .unwrap_or_default();
+                        info!(%authzid, "whoami success");
+                        Ok(authzid)
+                    } else {
+                        info!(?res.res.code);
+                        Err(LdapError::from(res.res.code))
+                    }
+                }
+                _ => Err(LdapError::InvalidProtocolState),
+            })
+    }
+
+    /// Upgrade a plaintext `ldap://` connection to TLS via the StartTLS
+    /// extended operation. This must be the first operation issued on the
+    /// connection, before `bind` - no application messages may be in flight
+    /// while the upgrade is in progress.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn start_tls(&mut self) -> LdapResult<()> {
+        let msgid = self.get_next_msgid();
+
+        let msg = LdapMsg {
+            msgid,
+            op: LdapOp::ExtendedRequest(LdapExtendedRequest {
+                name: OID_START_TLS.to_string(),
+                value: None,
+            }),
+            ctrl: vec![],
+        };
+
+        self.write_transport().send(msg).await?;
+
+        self.read_transport()
+            .next()
+            .await
+            .and_then(|msg| match msg.op {
+                LdapOp::ExtendedResponse(res) => {
+                    if res.res.code == LdapResultCode::Success {
+                        Ok(())
+                    } else {
+                        info!(?res.res.code);
+                        Err(LdapError::from(res.res.code))
+                    }
+                }
+                _ => Err(LdapError::InvalidProtocolState),
+            })?;
+
+        // Reclaim the underlying TcpStream from the plaintext framed halves
+        // so it can be re-wrapped in TLS. Pipelining anything before the
+        // upgrade completes is the classic STARTTLS plaintext-injection
+        // exposure, so refuse to silently discard bytes the codec already
+        // buffered past the ExtendedResponse.
+        let read_half = match self.read_transport.take() {
+            Some(LdapReadTransport::Plain(f)) => {
+                let parts = f.into_parts();
+                if !parts.read_buf.is_empty() {
+                    warn!(
+                        buffered = parts.read_buf.len(),
+                        "refusing to start tls - data already buffered past the StartTLS response"
+                    );
+                    self.read_transport =
+                        Some(LdapReadTransport::Plain(FramedRead::from_parts(parts)));
+                    return Err(LdapError::InvalidProtocolState);
+                }
+                parts.io
+            }
+            other => {
+                self.read_transport = other;
+                return Err(LdapError::InvalidProtocolState);
+            }
+        };
+
+        let write_half = match self.write_transport.take() {
+            Some(LdapWriteTransport::Plain(f)) => f.into_inner(),
+            other => {
+                self.write_transport = other;
+                return Err(LdapError::InvalidProtocolState);
+            }
+        };
+
+        let tcpstream = read_half.unsplit(write_half);
+
+        let (write_transport, read_transport) =
+            Self::tls_upgrade(tcpstream, &self.host, &self.tls_config).await?;
+        self.read_transport = Some(read_transport);
+        self.write_transport = Some(write_transport);
+
+        info!("starttls complete");
+        Ok(())
+    }
+}
+
+/// The response(s) a still-pending request is waiting for, keyed by msgid in
+/// [`LdapClientMuxInner::pending`].
+enum PendingResponder {
+    /// A single reply is expected, e.g. bind, whoami.
+    Single(oneshot::Sender<LdapResult<LdapMsg>>),
+    /// A stream of replies terminated by `SearchResultDone`, e.g. search.
+    Stream(mpsc::UnboundedSender<LdapResult<LdapMsg>>),
+}
+
+#[derive(Debug)]
+struct LdapClientMuxInner {
+    write_transport: AsyncMutex<LdapWriteTransport>,
+    pending: StdMutex<HashMap<i32, PendingResponder>>,
+    msg_counter: AtomicI32,
+    // Deliberately unused by default - subscribe via `notifications()` to
+    // observe unsolicited (msgid == 0) notifications, e.g. notices of
+    // disconnection.
+    notifications: broadcast::Sender<LdapMsg>,
+}
+
+impl fmt::Debug for PendingResponder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PendingResponder::Single(_) => write!(f, "Single"),
+            PendingResponder::Stream(_) => write!(f, "Stream"),
+        }
+    }
+}
+
+/// A pipelined `LdapClient` that allows many operations to be in flight at
+/// once over a single connection.
+///
+/// Unlike [`LdapClient`], which sends a request and immediately blocks on the
+/// next frame from the server, `LdapClientMux` owns a background task that
+/// demultiplexes incoming [`LdapMsg`]s by `msgid` and routes each to the
+/// caller that is waiting on it. This matches how LDAP actually works: a
+/// server may interleave replies to several outstanding requests, and a
+/// search reply is itself a stream of messages terminated by
+/// `SearchResultDone`. `LdapClientMux` is cheaply `Clone`, so the same
+/// connection can be shared between concurrent callers.
+#[derive(Debug, Clone)]
+pub struct LdapClientMux {
+    inner: Arc<LdapClientMuxInner>,
+}
+
+impl LdapClientMux {
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn new(url: &Url, timeout: Duration, tls_config: TlsConfig) -> LdapResult<Self> {
+        let (write_transport, read_transport, _host) = connect(url, timeout, &tls_config).await?;
+
+        let pending: Arc<StdMutex<HashMap<i32, PendingResponder>>> =
+            Arc::new(StdMutex::new(HashMap::new()));
+        let (notifications, _rx) = broadcast::channel(16);
+
+        let inner = Arc::new(LdapClientMuxInner {
+            write_transport: AsyncMutex::new(write_transport),
+            pending: pending.clone(),
+            msg_counter: AtomicI32::new(1),
+            notifications: notifications.clone(),
+        });
+
+        tokio::spawn(Self::demux_task(read_transport, pending, notifications));
+
+        Ok(LdapClientMux { inner })
+    }
+
+    /// Subscribe to unsolicited (`msgid == 0`) notifications from the
+    /// server, e.g. unsolicited notices of disconnection.
+    pub fn notifications(&self) -> broadcast::Receiver<LdapMsg> {
+        self.inner.notifications.subscribe()
+    }
+
+    fn get_next_msgid(&self) -> i32 {
+        self.inner.msg_counter.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn register(&self, msgid: i32, responder: PendingResponder) {
+        self.inner
+            .pending
+            .lock()
+            .expect("pending mutex poisoned")
+            .insert(msgid, responder);
+    }
+
+    fn deregister(&self, msgid: i32) {
+        self.inner
+            .pending
+            .lock()
+            .expect("pending mutex poisoned")
+            .remove(&msgid);
+    }
+
+    async fn send(&self, msg: LdapMsg) -> LdapResult<()> {
+        self.inner.write_transport.lock().await.send(msg).await
+    }
+
+    /// Own the read half of the connection, dispatching every frame that
+    /// arrives to whichever caller registered for its msgid. Runs until the
+    /// transport closes, at which point every still-pending caller is faulted
+    /// with `LdapError::TransportReadError`.
+    async fn demux_task(
+        mut read_transport: LdapReadTransport,
+        pending: Arc<StdMutex<HashMap<i32, PendingResponder>>>,
+        notifications: broadcast::Sender<LdapMsg>,
+    ) {
+        loop {
+            match read_transport.next().await {
+                Ok(msg) => {
+                    let msgid = msg.msgid;
+                    if msgid == 0 {
+                        // Unsolicited notification - no request is waiting on this.
+                        let _ = notifications.send(msg);
+                        continue;
+                    }
+
+                    let mut pending_guard = pending.lock().expect("pending mutex poisoned");
+                    match pending_guard.remove(&msgid) {
+                        Some(PendingResponder::Single(tx)) => {
+                            let _ = tx.send(Ok(msg));
+                        }
+                        Some(PendingResponder::Stream(tx)) => {
+                            let done = matches!(msg.op, LdapOp::SearchResultDone(_));
+                            let _ = tx.send(Ok(msg));
+                            if !done {
+                                pending_guard.insert(msgid, PendingResponder::Stream(tx));
+                            }
+                        }
+                        None => {
+                            info!(msgid, "response for a request we are no longer waiting on");
+                        }
+                    }
+                }
+                Err(e) => {
+                    info!(?e, "transport closed, faulting all pending requests");
+                    let mut pending_guard = pending.lock().expect("pending mutex poisoned");
+                    for (_, responder) in pending_guard.drain() {
+                        match responder {
+                            PendingResponder::Single(tx) => {
+                                let _ = tx.send(Err(LdapError::TransportReadError));
+                            }
+                            PendingResponder::Stream(tx) => {
+                                let _ = tx.send(Err(LdapError::TransportReadError));
+                            }
+                        }
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn bind(&self, dn: String, pw: String) -> LdapResult<()> {
+        info!(%dn);
+        let msgid = self.get_next_msgid();
+        let (tx, rx) = oneshot::channel();
+        self.register(msgid, PendingResponder::Single(tx));
+
+        let msg = LdapMsg {
+            msgid,
+            op: LdapOp::BindRequest(LdapBindRequest {
+                dn,
+                cred: LdapBindCred::Simple(pw),
+            }),
+            ctrl: vec![],
+        };
+
+        if let Err(e) = self.send(msg).await {
+            self.deregister(msgid);
+            return Err(e);
+        }
+
+        let msg = rx.await.map_err(|_| LdapError::TransportReadError)??;
+        match msg.op {
+            LdapOp::BindResponse(res) => {
+                if res.res.code == LdapResultCode::Success {
+                    info!("bind success");
+                    Ok(())
+                } else {
+                    info!(?res.res.code);
+                    Err(LdapError::from(res.res.code))
+                }
+            }
+            _ => Err(LdapError::InvalidProtocolState),
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search(
+        &self,
+        base: String,
+        scope: LdapSearchScope,
+        aliases: LdapDerefAliases,
+        sizelimit: Option<i32>,
+        timelimit: Option<i32>,
+        typesonly: bool,
+        filter: LdapFilter,
+        attrs: Vec<String>,
+    ) -> LdapResult<LdapSearchResult> {
+        info!(%base);
+        let msgid = self.get_next_msgid();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        self.register(msgid, PendingResponder::Stream(tx));
+
+        let msg = LdapMsg {
+            msgid,
+            op: LdapOp::SearchRequest(LdapSearchRequest {
+                base,
+                scope,
+                aliases,
+                sizelimit: sizelimit.unwrap_or(0),
+                timelimit: timelimit.unwrap_or(0),
+                typesonly,
+                filter,
+                attrs,
+            }),
+            ctrl: vec![],
+        };
+
+        if let Err(e) = self.send(msg).await {
+            self.deregister(msgid);
+            return Err(e);
+        }
+
+        let mut entries = Vec::new();
+        let mut referrals = Vec::new();
+
+        loop {
+            let msg = rx.recv().await.ok_or(LdapError::TransportReadError)??;
+
+            match msg.op {
+                LdapOp::SearchResultEntry(entry) => entries.push(entry),
+                LdapOp::SearchResultReference(reference) => referrals.extend(reference.uris),
+                LdapOp::SearchResultDone(res) => {
+                    return if res.code == LdapResultCode::Success {
+                        info!(entries = %entries.len(), "search success");
+                        Ok(LdapSearchResult { entries, referrals })
+                    } else {
+                        info!(?res.code);
+                        Err(LdapError::from(res.code))
+                    };
+                }
+                _ => {
+                    self.deregister(msgid);
+                    return Err(LdapError::InvalidProtocolState);
+                }
+            }
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn whoami(&self) -> LdapResult<String> {
+        let msgid = self.get_next_msgid();
+        let (tx, rx) = oneshot::channel();
+        self.register(msgid, PendingResponder::Single(tx));
+
+        let msg = LdapMsg {
+            msgid,
+            op: LdapOp::ExtendedRequest(LdapExtendedRequest {
+                name: OID_WHOAMI.to_string(),
+                value: None,
+            }),
+            ctrl: vec![],
+        };
+
+        if let Err(e) = self.send(msg).await {
+            self.deregister(msgid);
+            return Err(e);
+        }
+
+        let msg = rx.await.map_err(|_| LdapError::TransportReadError)??;
+        match msg.op {
+            LdapOp::ExtendedResponse(res) => {
+                if res.res.code == LdapResultCode::Success {
+                    let authzid = res
+                        .value
+                        .map(|v| String::from_utf8_lossy(&v).into_owned())
+                        .unwrap_or_default();
+                    info!(%authzid, "whoami success");
+                    Ok(authzid)
+                } else {
+                    info!(?res.res.code);
+                    Err(LdapError::from(res.res.code))
+                }
+            }
+            _ => Err(LdapError::InvalidProtocolState),
+        }
+    }
+
+    /// Abandon a still-outstanding operation. The directory server sends no
+    /// response to an abandon, so this only confirms the request was written
+    /// to the transport - the abandoned operation's caller (if any is still
+    /// waiting locally) is faulted with `LdapError::Abandoned`.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn abandon(&self, target_msgid: i32) -> LdapResult<()> {
+        let msgid = self.get_next_msgid();
+
+        let msg = LdapMsg {
+            msgid,
+            op: LdapOp::AbandonRequest(target_msgid),
+            ctrl: vec![],
+        };
+
+        self.send(msg).await?;
+
+        if let Some(responder) = self
+            .inner
+            .pending
+            .lock()
+            .expect("pending mutex poisoned")
+            .remove(&target_msgid)
+        {
+            match responder {
+                PendingResponder::Single(tx) => {
+                    let _ = tx.send(Err(LdapError::Abandoned));
+                }
+                PendingResponder::Stream(tx) => {
+                    let _ = tx.send(Err(LdapError::Abandoned));
+                }
+            }
+        }
+
+        Ok(())
+    }
 }